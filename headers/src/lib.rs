@@ -0,0 +1,62 @@
+/// HTTP header names this crate knows how to render.
+#[allow(dead_code)]
+#[derive(Clone, Copy, PartialEq)]
+pub enum Header {
+    ContentLength,
+    ContentType,
+    Connection,
+    TransferEncoding,
+}
+
+impl Header {
+    fn raw(&self) -> &'static str {
+        match self {
+            Header::ContentLength => "Content-Length",
+            Header::ContentType => "Content-Type",
+            Header::Connection => "Connection",
+            Header::TransferEncoding => "Transfer-Encoding",
+        }
+    }
+}
+
+/// An ordered collection of response headers.
+#[derive(Default)]
+pub struct Headers {
+    entries: Vec<(Header, String)>,
+}
+
+impl Headers {
+    pub fn add(&mut self, header: Header, value: String) {
+        self.entries.push((header, value));
+    }
+
+    pub fn raw(&self) -> Vec<u8> {
+        let mut raw = Vec::new();
+        for (header, value) in &self.entries {
+            raw.extend_from_slice(header.raw().as_bytes());
+            raw.extend_from_slice(b": ");
+            raw.extend_from_slice(value.as_bytes());
+            raw.extend_from_slice(b"\r\n");
+        }
+        raw.extend_from_slice(b"\r\n");
+
+        raw
+    }
+}
+
+/// Media types this crate can set via `Content-Type`.
+#[allow(dead_code)]
+#[derive(Clone, Copy, PartialEq)]
+pub enum MediaType {
+    PlainText,
+    ApplicationJson,
+}
+
+impl MediaType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MediaType::PlainText => "text/plain",
+            MediaType::ApplicationJson => "application/json",
+        }
+    }
+}
@@ -1,6 +1,11 @@
+use std::cell::RefCell;
+use std::io::{self, Write};
+
 use ascii::{CR, LF, SP};
 use common::{Body, Version};
 use headers::{Header, Headers, MediaType};
+use serde::Serialize;
+use serde_json;
 
 #[allow(dead_code)]
 #[derive(Clone, Copy, PartialEq)]
@@ -10,16 +15,53 @@ pub enum StatusCode {
     NotFound,
     InternalServerError,
     NotImplemented,
+    /// Any status code not covered by the named variants above, e.g.
+    /// `204 No Content` or `409 Conflict`. Carries its own reason phrase
+    /// since there's no table to look it up in.
+    Custom(u16, &'static str),
 }
 
 impl StatusCode {
-    fn raw(&self) -> &'static [u8] {
+    fn raw(&self) -> Vec<u8> {
+        match self {
+            StatusCode::OK => b"200".to_vec(),
+            StatusCode::BadRequest => b"400".to_vec(),
+            StatusCode::NotFound => b"404".to_vec(),
+            StatusCode::InternalServerError => b"500".to_vec(),
+            StatusCode::NotImplemented => b"501".to_vec(),
+            StatusCode::Custom(code, _) => code.to_string().into_bytes(),
+        }
+    }
+
+    /// Returns the textual reason phrase associated with this status code,
+    /// e.g. `OK` for `200`.
+    pub fn reason_phrase(&self) -> &'static str {
+        match self {
+            StatusCode::OK => "OK",
+            StatusCode::BadRequest => "Bad Request",
+            StatusCode::NotFound => "Not Found",
+            StatusCode::InternalServerError => "Internal Server Error",
+            StatusCode::NotImplemented => "Not Implemented",
+            StatusCode::Custom(_, reason) => reason,
+        }
+    }
+}
+
+/// Whether the underlying connection should be kept open for reuse or
+/// closed once the response has been sent, as surfaced via the
+/// `Connection` header on `HTTP/1.1` responses.
+#[allow(dead_code)]
+#[derive(Clone, Copy, PartialEq)]
+pub enum ConnectionType {
+    Close,
+    KeepAlive,
+}
+
+impl ConnectionType {
+    fn raw(&self) -> &'static str {
         match self {
-            StatusCode::OK => b"200",
-            StatusCode::BadRequest => b"400",
-            StatusCode::NotFound => b"404",
-            StatusCode::InternalServerError => b"500",
-            StatusCode::NotImplemented => b"501",
+            ConnectionType::Close => "close",
+            ConnectionType::KeepAlive => "keep-alive",
         }
     }
 }
@@ -31,8 +73,12 @@ struct StatusLine {
 
 impl StatusLine {
     fn new(status_code: StatusCode) -> Self {
+        StatusLine::new_with_version(status_code, Version::Http10)
+    }
+
+    fn new_with_version(status_code: StatusCode, http_version: Version) -> Self {
         return StatusLine {
-            http_version: Version::Http10,
+            http_version,
             status_code,
         };
     }
@@ -40,8 +86,111 @@ impl StatusLine {
     fn raw(&self) -> Vec<u8> {
         let http_version = self.http_version.raw();
         let status_code = self.status_code.raw();
+        let reason_phrase = self.status_code.reason_phrase().as_bytes();
+
+        return [
+            http_version.to_vec(),
+            vec![SP],
+            status_code,
+            vec![SP],
+            reason_phrase.to_vec(),
+            vec![CR, LF],
+        ]
+        .concat();
+    }
+}
+
+/// Size hint for a [`MessageBody`]: either a known length, suitable for
+/// `Content-Length` framing, or an unknown-length stream that must be
+/// framed with `Transfer-Encoding: chunked` instead.
+pub enum BodySize {
+    Sized(u64),
+    Stream,
+}
+
+/// A pull-based source of response body bytes, as an alternative to
+/// buffering the whole payload up front in a [`Body`]. Lets callers stream
+/// large or generated payloads (e.g. metrics dumps) one chunk at a time.
+pub trait MessageBody {
+    /// Hints whether the total size is known ahead of time.
+    fn size(&self) -> BodySize;
+
+    /// Pulls the next chunk of body bytes, or `None` once exhausted.
+    fn next_chunk(&mut self) -> Option<Vec<u8>>;
+}
+
+/// Wraps a [`MessageBody`] together with the framing it was attached with.
+///
+/// `write_to` pulls and frames one chunk at a time straight onto a writer,
+/// so large or generated payloads never need to be resident in memory all
+/// at once. `raw()` is the buffered counterpart for callers (and `Response`
+/// tests) that need the framed bytes as a single `Vec<u8>`; `raw()` needs to
+/// stay a pure `&self` method callers can invoke more than once, but pulling
+/// chunks out of a `MessageBody` is inherently a one-shot, mutating
+/// operation, so the `RefCell`s let the first call drain the source lazily
+/// and cache the result for later calls to replay.
+struct StreamBody {
+    source: RefCell<Box<dyn MessageBody>>,
+    chunked: bool,
+    cache: RefCell<Option<Vec<u8>>>,
+}
+
+impl StreamBody {
+    fn new(source: Box<dyn MessageBody>) -> Self {
+        let chunked = matches!(source.size(), BodySize::Stream);
+        StreamBody {
+            source: RefCell::new(source),
+            chunked,
+            cache: RefCell::new(None),
+        }
+    }
+
+    /// Pulls every remaining chunk from the source and writes the framed
+    /// bytes to `writer` as they arrive, without buffering the whole body.
+    ///
+    /// If `raw()` already drained and cached the source, replays the cache
+    /// instead — the source is one-shot, so this keeps `write_to` after
+    /// `raw()` consistent. A `write_to` that finds no cache drains the source
+    /// without caching it, so a later `raw()` or `write_to` on the same body
+    /// sees an exhausted source.
+    fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        if let Some(cached) = self.cache.borrow().as_ref() {
+            return writer.write_all(cached);
+        }
+
+        let mut source = self.source.borrow_mut();
+        while let Some(chunk) = source.next_chunk() {
+            if chunk.is_empty() {
+                // An empty chunk would render as `0\r\n\r\n`, indistinguishable
+                // from the chunked terminator, so there's nothing to frame.
+                continue;
+            }
+            if self.chunked {
+                write!(writer, "{:x}\r\n", chunk.len())?;
+                writer.write_all(&chunk)?;
+                writer.write_all(b"\r\n")?;
+            } else {
+                writer.write_all(&chunk)?;
+            }
+        }
+        if self.chunked {
+            writer.write_all(b"0\r\n\r\n")?;
+        }
+
+        Ok(())
+    }
+
+    fn raw(&self) -> Vec<u8> {
+        if let Some(cached) = self.cache.borrow().as_ref() {
+            return cached.clone();
+        }
+
+        let mut body = Vec::new();
+        self.write_to(&mut body)
+            .expect("writing to a Vec<u8> is infallible");
 
-        return [http_version, &[SP], status_code, &[SP, CR, LF]].concat();
+        *self.cache.borrow_mut() = Some(body.clone());
+        body
     }
 }
 
@@ -49,6 +198,7 @@ pub struct Response {
     status_line: StatusLine,
     headers: Headers,
     body: Option<Body>,
+    stream: Option<StreamBody>,
 }
 
 impl Response {
@@ -57,19 +207,78 @@ impl Response {
             status_line: StatusLine::new(status_code),
             headers: Headers::default(),
             body: None,
+            stream: None,
+        };
+    }
+
+    /// Builds a response with an explicit HTTP version, e.g. to opt into
+    /// `HTTP/1.1` so the connection can subsequently be kept alive.
+    pub fn new_with_version(status_code: StatusCode, http_version: Version) -> Response {
+        return Response {
+            status_line: StatusLine::new_with_version(status_code, http_version),
+            headers: Headers::default(),
+            body: None,
+            stream: None,
         };
     }
 
+    /// Starts building a [`Response`] with the given status code.
+    pub fn build(status_code: StatusCode) -> ResponseBuilder {
+        ResponseBuilder::new(status_code)
+    }
+
+    /// Sets the `Connection` header, indicating whether the socket this
+    /// response is written to should be kept open for reuse.
+    ///
+    /// `ConnectionType::KeepAlive` is a no-op on `HTTP/1.0`: persistent
+    /// connections are an `HTTP/1.1` feature, so advertising keep-alive on a
+    /// 1.0 response would be misleading. `Close` is always safe to set.
+    pub fn set_connection_type(&mut self, connection_type: ConnectionType) {
+        if connection_type == ConnectionType::KeepAlive
+            && self.status_line.http_version != Version::Http11
+        {
+            return;
+        }
+        self.headers.add(Header::Connection, String::from(connection_type.raw()));
+    }
+
     pub fn set_body(&mut self, body: Body) {
-        self.headers
-            .add(Header::ContentLength, body.len().to_string());
-        self.headers.add(
-            Header::ContentType,
-            String::from(MediaType::PlainText.as_str()),
-        );
+        self.headers.add(Header::ContentLength, body.len().to_string());
+        self.headers.add(Header::ContentType, String::from(MediaType::PlainText.as_str()));
         self.body = Some(body);
     }
 
+    /// Serializes `value` as JSON and sets it as the response body, with
+    /// `Content-Length` and `Content-Type: application/json` set accordingly.
+    ///
+    /// Returns the `serde_json` error if `value` fails to serialize.
+    pub fn set_json<T: Serialize>(&mut self, value: &T) -> Result<(), serde_json::Error> {
+        let body = Body::new(serde_json::to_string(value)?);
+        self.headers.add(Header::ContentLength, body.len().to_string());
+        self.headers.add(Header::ContentType, String::from(MediaType::ApplicationJson.as_str()));
+        self.body = Some(body);
+        Ok(())
+    }
+
+    /// Attaches a streaming body source in place of an in-memory [`Body`].
+    ///
+    /// Sets `Content-Length` when `stream` reports a known size, or
+    /// `Transfer-Encoding: chunked` otherwise, so `raw()` can frame the
+    /// pulled chunks accordingly. Chunked framing is an `HTTP/1.1`-only
+    /// feature, so an unsized stream also bumps the response to `HTTP/1.1`.
+    pub fn set_stream(&mut self, stream: Box<dyn MessageBody>) {
+        match stream.size() {
+            BodySize::Sized(len) => {
+                self.headers.add(Header::ContentLength, len.to_string());
+            }
+            BodySize::Stream => {
+                self.headers.add(Header::TransferEncoding, String::from("chunked"));
+                self.status_line.http_version = Version::Http11;
+            }
+        }
+        self.stream = Some(StreamBody::new(stream));
+    }
+
     fn body_raw(&self) -> &[u8] {
         match self.body {
             Some(ref body) => body.raw(),
@@ -80,13 +289,30 @@ impl Response {
     pub fn raw(&self) -> Vec<u8> {
         let status_line = self.status_line.raw();
         let headers = self.headers.raw();
-        let body = self.body_raw();
+        let body = match self.stream {
+            Some(ref stream) => stream.raw(),
+            None => self.body_raw().to_owned(),
+        };
 
-        let response = [status_line, headers, body.to_owned()].concat();
+        let response = [status_line, headers, body].concat();
 
         return response;
     }
 
+    /// Writes the response directly to `writer`, pulling a streaming body's
+    /// chunks one at a time instead of buffering the whole payload first —
+    /// the no-buffering counterpart to `raw()` for large or generated
+    /// payloads (e.g. metrics dumps).
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&self.status_line.raw())?;
+        writer.write_all(&self.headers.raw())?;
+
+        match self.stream {
+            Some(ref stream) => stream.write_to(writer),
+            None => writer.write_all(self.body_raw()),
+        }
+    }
+
     pub fn status(&self) -> StatusCode {
         self.status_line.status_code
     }
@@ -96,6 +322,100 @@ impl Response {
     }
 }
 
+/// Fluent builder for assembling a [`Response`] one piece at a time.
+///
+/// Mirrors the `Response::build(status) -> ResponseBuilder` pattern: unlike
+/// `Response::set_body`, which always forces `Content-Length`/`Content-Type`,
+/// this lets callers attach arbitrary headers before finalizing the body.
+pub struct ResponseBuilder {
+    status_code: StatusCode,
+    http_version: Version,
+    headers: Headers,
+    body: Option<Body>,
+    stream: Option<StreamBody>,
+}
+
+impl ResponseBuilder {
+    fn new(status_code: StatusCode) -> Self {
+        ResponseBuilder {
+            status_code,
+            http_version: Version::Http10,
+            headers: Headers::default(),
+            body: None,
+            stream: None,
+        }
+    }
+
+    /// Sets the HTTP version of the response, e.g. `Version::Http11`.
+    pub fn version(mut self, http_version: Version) -> Self {
+        self.http_version = http_version;
+        self
+    }
+
+    /// Adds a single header to the response under construction.
+    pub fn header(mut self, header: Header, value: String) -> Self {
+        self.headers.add(header, value);
+        self
+    }
+
+    /// Sets the `Content-Type` header.
+    pub fn content_type(self, media_type: MediaType) -> Self {
+        self.header(Header::ContentType, String::from(media_type.as_str()))
+    }
+
+    /// Sets the `Connection` header.
+    pub fn connection_type(self, connection_type: ConnectionType) -> Self {
+        self.header(Header::Connection, String::from(connection_type.raw()))
+    }
+
+    /// Sets the response body, without touching `Content-Length`/`Content-Type`.
+    ///
+    /// Use `.content_type(...)` explicitly if the body requires one; this
+    /// leaves headers entirely under the caller's control.
+    pub fn body(mut self, body: Body) -> Self {
+        self.body = Some(body);
+        self
+    }
+
+    /// Serializes `value` as JSON, setting the body, `Content-Length`, and
+    /// `Content-Type: application/json` in one step — matching what
+    /// `Response::set_json` sets.
+    ///
+    /// Returns the `serde_json` error if `value` fails to serialize.
+    pub fn json<T: Serialize>(self, value: &T) -> Result<Self, serde_json::Error> {
+        let body = Body::new(serde_json::to_string(value)?);
+        Ok(self
+            .header(Header::ContentLength, body.len().to_string())
+            .content_type(MediaType::ApplicationJson)
+            .body(body))
+    }
+
+    /// Attaches a streaming body source, setting `Content-Length` or
+    /// `Transfer-Encoding: chunked` depending on `stream`'s size hint.
+    /// Chunked framing is an `HTTP/1.1`-only feature, so an unsized stream
+    /// also bumps the response to `HTTP/1.1`.
+    pub fn stream(mut self, stream: Box<dyn MessageBody>) -> Self {
+        self = match stream.size() {
+            BodySize::Sized(len) => self.header(Header::ContentLength, len.to_string()),
+            BodySize::Stream => self
+                .header(Header::TransferEncoding, String::from("chunked"))
+                .version(Version::Http11),
+        };
+        self.stream = Some(StreamBody::new(stream));
+        self
+    }
+
+    /// Finalizes the builder into a [`Response`].
+    pub fn build(self) -> Response {
+        Response {
+            status_line: StatusLine::new_with_version(self.status_code, self.http_version),
+            headers: self.headers,
+            body: self.body,
+            stream: self.stream,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -111,11 +431,11 @@ mod tests {
         let content_length = format!("Content-Length: {}\r\n", body.len());
 
         let expected_response_1 = format!(
-            "HTTP/1.0 200 \r\n{}{}\r\nThis is a test",
+            "HTTP/1.0 200 OK\r\n{}{}\r\nThis is a test",
             content_length, content_type
         );
         let expected_response_2 = format!(
-            "HTTP/1.0 200 \r\n{}{}\r\nThis is a test",
+            "HTTP/1.0 200 OK\r\n{}{}\r\nThis is a test",
             content_type, content_length
         );
 
@@ -124,4 +444,153 @@ mod tests {
                 || response.raw() == expected_response_2.into_bytes()
         );
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_builder() {
+        let body = String::from("This is a test");
+        let response = Response::build(StatusCode::OK)
+            .content_type(MediaType::PlainText)
+            .header(Header::ContentLength, body.len().to_string())
+            .body(Body::new(body.clone()))
+            .build();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.body().unwrap().raw(), body.as_bytes());
+    }
+
+    #[test]
+    fn test_builder_json() {
+        #[derive(Serialize)]
+        struct Point {
+            x: u32,
+            y: u32,
+        }
+
+        let point = Point { x: 1, y: 2 };
+        let expected_body = serde_json::to_string(&point).unwrap();
+        let response = Response::build(StatusCode::OK).json(&point).unwrap().build();
+
+        let raw = String::from_utf8(response.raw()).unwrap();
+        assert!(raw.contains("Content-Type: application/json\r\n"));
+        assert!(raw.contains(&format!("Content-Length: {}\r\n", expected_body.len())));
+        assert!(raw.ends_with(&expected_body));
+    }
+
+    #[test]
+    fn test_http11_keep_alive() {
+        let mut response = Response::new_with_version(StatusCode::OK, Version::Http11);
+        response.set_connection_type(ConnectionType::KeepAlive);
+
+        let raw = response.raw();
+        let raw = String::from_utf8(raw).unwrap();
+
+        assert!(raw.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(raw.contains("Connection: keep-alive\r\n"));
+    }
+
+    #[test]
+    fn test_keep_alive_ignored_on_http10() {
+        let mut response = Response::new(StatusCode::OK);
+        response.set_connection_type(ConnectionType::KeepAlive);
+
+        let raw = String::from_utf8(response.raw()).unwrap();
+        assert!(!raw.contains("Connection"));
+    }
+
+    #[test]
+    fn test_set_json() {
+        #[derive(Serialize)]
+        struct Point {
+            x: u32,
+            y: u32,
+        }
+
+        let mut response = Response::new(StatusCode::OK);
+        let point = Point { x: 1, y: 2 };
+        response.set_json(&point).unwrap();
+
+        let expected_body = serde_json::to_string(&point).unwrap();
+        let raw = String::from_utf8(response.raw()).unwrap();
+
+        assert!(raw.contains("Content-Type: application/json\r\n"));
+        assert!(raw.contains(&format!("Content-Length: {}\r\n", expected_body.len())));
+        assert!(raw.ends_with(&expected_body));
+    }
+
+    struct TestStream {
+        chunks: Vec<&'static str>,
+    }
+
+    impl MessageBody for TestStream {
+        fn size(&self) -> BodySize {
+            BodySize::Stream
+        }
+
+        fn next_chunk(&mut self) -> Option<Vec<u8>> {
+            if self.chunks.is_empty() {
+                None
+            } else {
+                Some(self.chunks.remove(0).as_bytes().to_owned())
+            }
+        }
+    }
+
+    #[test]
+    fn test_stream_chunked() {
+        let mut response = Response::new(StatusCode::OK);
+        response.set_stream(Box::new(TestStream {
+            chunks: vec!["hello ", "world"],
+        }));
+
+        // An unsized stream forces the response onto HTTP/1.1, since chunked framing
+        // doesn't exist on HTTP/1.0.
+        let raw = String::from_utf8(response.raw()).unwrap();
+        assert!(raw.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(raw.contains("Transfer-Encoding: chunked\r\n"));
+        assert!(raw.ends_with("6\r\nhello \r\n5\r\nworld\r\n0\r\n\r\n"));
+
+        // raw() is idempotent: calling it again must not drain the stream
+        // further or return a different/empty body.
+        assert_eq!(response.raw(), raw.into_bytes());
+    }
+
+    #[test]
+    fn test_stream_skips_empty_chunks() {
+        let mut response = Response::new(StatusCode::OK);
+        response.set_stream(Box::new(TestStream {
+            chunks: vec!["hello ", "", "world"],
+        }));
+
+        // An empty chunk must not be framed as `0\r\n\r\n`, since that's the
+        // chunked terminator and would truncate the real chunks after it.
+        let raw = String::from_utf8(response.raw()).unwrap();
+        assert!(raw.ends_with("6\r\nhello \r\n5\r\nworld\r\n0\r\n\r\n"));
+    }
+
+    #[test]
+    fn test_write_to() {
+        let mut response = Response::new(StatusCode::OK);
+        response.set_stream(Box::new(TestStream {
+            chunks: vec!["hello ", "world"],
+        }));
+
+        let mut written = Vec::new();
+        response.write_to(&mut written).unwrap();
+        let written = String::from_utf8(written).unwrap();
+
+        assert!(written.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(written.contains("Transfer-Encoding: chunked\r\n"));
+        assert!(written.ends_with("6\r\nhello \r\n5\r\nworld\r\n0\r\n\r\n"));
+    }
+
+    #[test]
+    fn test_custom_status_code() {
+        let status_code = StatusCode::Custom(409, "Conflict");
+        assert_eq!(status_code.reason_phrase(), "Conflict");
+
+        let mut response = Response::new(status_code);
+        let raw = String::from_utf8(response.raw()).unwrap();
+
+        assert!(raw.starts_with("HTTP/1.0 409 Conflict\r\n"));
+    }
+}